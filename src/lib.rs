@@ -5,14 +5,19 @@
 
 
 use fuji::Fuji;
-use crate::model::{ModelBuilder, Mesh};
-use vulkano::pipeline::GraphicsPipeline;
+use crate::model::{Model, ModelBuilder, ModelSource, Mesh, Vertex as ModelVertex};
+use vulkano::pipeline::{GraphicsPipeline, GraphicsPipelineAbstract};
 use std::sync::Arc;
+use std::sync::mpsc::channel;
+use std::thread;
+use std::time::Duration;
 use vulkano::pipeline::vertex::{Vertex};
+use notify::{watcher, DebouncedEvent, RecommendedWatcher, RecursiveMode, Watcher};
 
 
 pub mod model;
 pub mod camera;
+pub mod worker;
 
 pub struct FrozenGameBuilder {
     fuji: Fuji,
@@ -46,4 +51,61 @@ impl FrozenGameInstance {
     {
         ModelBuilder::new(self.fuji.graphics_queue().clone(), pipeline)
     }
+
+    /// Watch the source file a [`Model`] was loaded from and rebuild its buffers in place whenever
+    /// the file changes on disk.
+    ///
+    /// Only models created through `with_obj_path`/`with_gltf_path` carry a source path; for any
+    /// other model this returns `None`. The returned [`ReloadHandle`] owns the watcher — drop it to
+    /// stop watching. Reloads swap the model's `vertex_buffer`/`index_buffer` `Arc`s atomically once
+    /// the new upload completes, so an artist can edit an asset and see it update without a restart.
+    pub fn watch_model<VD, L, RP>(&self, model: &Model<VD, ModelVertex, u32, L, RP>) -> Option<ReloadHandle>
+        where
+            VD: Send + Sync + 'static,
+            L: Send + Sync + 'static,
+            RP: Send + Sync + 'static,
+            GraphicsPipeline<VD, L, RP>: GraphicsPipelineAbstract + Send + Sync + 'static,
+    {
+        let source = model.source.clone()?;
+        let queue = self.fuji.graphics_queue().clone();
+        let pipeline = model.pipeline.clone();
+        let vertex_slot = model.vertex_buffer.clone();
+        let index_slot = model.index_buffer.clone();
+
+        let (tx, rx) = channel();
+        let mut watcher = watcher(tx, Duration::from_millis(500)).ok()?;
+        watcher.watch(source.path(), RecursiveMode::NonRecursive).ok()?;
+
+        let handle = thread::spawn(move || {
+            while let Ok(event) = rx.recv() {
+                match event {
+                    DebouncedEvent::Write(_) | DebouncedEvent::Create(_) | DebouncedEvent::Rename(_, _) => {
+                        let builder = ModelBuilder::new(queue.clone(), pipeline.clone());
+                        let builder = match &source {
+                            ModelSource::Obj(path) => builder.with_obj_path(path),
+                            ModelSource::Gltf(path) => builder.with_gltf_path(path),
+                        };
+
+                        if let Ok(reloaded) = builder.build() {
+                            *vertex_slot.lock().unwrap() = reloaded.vertex_buffer.lock().unwrap().clone();
+                            *index_slot.lock().unwrap() = reloaded.index_buffer.lock().unwrap().clone();
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        });
+
+        Some(ReloadHandle {
+            _watcher: watcher,
+            _thread: handle,
+        })
+    }
+}
+
+/// Keeps a model's filesystem watch alive. Dropping it unregisters the watcher and ends the
+/// reload thread.
+pub struct ReloadHandle {
+    _watcher: RecommendedWatcher,
+    _thread: thread::JoinHandle<()>,
 }
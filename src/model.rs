@@ -1,9 +1,10 @@
 use std::io::{BufRead, BufReader, Read};
 use std::collections::HashMap;
 use itertools::Itertools;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
 use std::fs::File;
-use vulkano::device::{Device, Queue};
+use vulkano::device::{Device, DeviceOwned, Queue};
 use std::sync::Arc;
 use vulkano::pipeline::shader::{ShaderModule, GraphicsEntryPointAbstract, SpecializationConstants};
 use vulkano::pipeline::{GraphicsPipeline, GraphicsPipelineAbstract};
@@ -11,14 +12,27 @@ use vulkano::buffer::{CpuAccessibleBuffer, ImmutableBuffer, BufferUsage, TypedBu
 use crate::model::ModelBuilderError::MissingMeshes;
 use vulkano::descriptor::PipelineLayoutAbstract;
 use vulkano::framebuffer::RenderPassAbstract;
-use vulkano::command_buffer::{AutoCommandBufferBuilder, DynamicState, DrawIndexedError};
+use vulkano::command_buffer::{AutoCommandBufferBuilder, AutoCommandBuffer, BuildError, CommandBufferExecError, DynamicState, DrawIndexedError};
 use vulkano::command_buffer::pool::standard::StandardCommandPoolBuilder;
+use vulkano::command_buffer::submit::SubmitAnyBuilder;
+use vulkano::sync::{AccessCheckError, AccessFlagBits, FlushError, PipelineStages};
+use vulkano::image::{ImageAccess, ImageLayout};
 use vulkano::SafeDeref;
 use vulkano::descriptor::pipeline_layout::PipelineLayoutDesc;
 use vulkano::pipeline::input_assembly::Index;
-use vulkano::descriptor::descriptor_set::DescriptorSetsCollection;
+use vulkano::descriptor::descriptor_set::{DescriptorSet, DescriptorSetsCollection, PersistentDescriptorSet};
+use vulkano::descriptor::descriptor::DescriptorDesc;
+use vulkano::image::{Dimensions, ImmutableImage};
+use vulkano::format::Format;
+use vulkano::sampler::Sampler;
 use vulkano::pipeline::vertex::{VertexSource};
+use vulkano::sync::GpuFuture;
+use gltf::mesh::util::ReadIndices;
 use std::marker::PhantomData;
+use std::sync::mpsc::{channel, Receiver};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::any::Any;
+use crate::worker::UploadWorker;
 
 impl From<tobj::Mesh> for Mesh<Vertex, u32> {
     fn from(mesh: tobj::Mesh) -> Mesh<Vertex, u32> {
@@ -46,12 +60,17 @@ impl From<&tobj::Mesh> for Mesh<Vertex, u32> {
                     *mesh.normals.get((i * 3) + 1).unwrap(),
                     *mesh.normals.get((i * 3) + 2).unwrap(),
                 ],
+                uv: [
+                    mesh.texcoords.get(i * 2).copied().unwrap_or(0.0),
+                    mesh.texcoords.get((i * 2) + 1).copied().unwrap_or(0.0),
+                ],
             });
         }
 
         Mesh {
             vertices,
             indices,
+            material_id: mesh.material_id,
         }
     }
 }
@@ -61,6 +80,26 @@ pub struct ModelBuilder<VertexDefinition, VertexType, IndexType, Layout, RenderP
     queue:                Arc<Queue>,
     meshes:               Option<Vec<Mesh<VertexType, IndexType>>>,
     pipeline:             Arc<GraphicsPipeline<VertexDefinition, Layout, RenderP>>,
+    instance_buffer:      Option<Arc<CpuAccessibleBuffer<[InstanceData]>>>,
+    materials:            Vec<tobj::Material>,
+    source:               Option<ModelSource>,
+}
+
+/// The on-disk source a [`Model`] was loaded from, retained so the asset-watch subsystem can
+/// rebuild its buffers when the file changes.
+#[derive(Clone)]
+pub enum ModelSource {
+    Obj(PathBuf),
+    Gltf(PathBuf),
+}
+
+impl ModelSource {
+    /// The watched file path, regardless of format.
+    pub fn path(&self) -> &Path {
+        match self {
+            ModelSource::Obj(path) | ModelSource::Gltf(path) => path,
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -68,6 +107,7 @@ pub enum ModelBuilderError {
     MissingMeshes,
     MissingVertexShader,
     MissingFragmentShader,
+    WorkerUnavailable,
 }
 
 impl<VertexDefinition, VertexType, IndexType, Layout, RenderP> ModelBuilder<VertexDefinition, VertexType, IndexType, Layout, RenderP>
@@ -80,15 +120,32 @@ impl<VertexDefinition, VertexType, IndexType, Layout, RenderP> ModelBuilder<Vert
         ModelBuilder {
             queue,
             pipeline,
-            meshes:   None,
+            meshes:          None,
+            instance_buffer: None,
+            materials:       Vec::new(),
+            source:          None,
+        }
+    }
+
+    /// Attach a per-instance transform buffer so the resulting [`Model`] can be drawn many times
+    /// in a single call through [`Model::draw_instanced`]. The caller keeps the `Arc` and may
+    /// update the buffer contents each frame without re-uploading the mesh geometry.
+    pub fn with_instance_buffer(self, instance_buffer: Arc<CpuAccessibleBuffer<[InstanceData]>>) -> Self {
+        Self {
+            instance_buffer: Some(instance_buffer),
+            ..self
         }
     }
 
     pub fn with_obj_path(self, obj_path: &Path) -> Self {
-        if let Ok(f) = File::open(obj_path) {
-            self.with_obj(&mut BufReader::new(f))
-        } else {
-            self
+        match tobj::load_obj(obj_path) {
+            Ok((models, materials)) => Self {
+                meshes: Some(models.into_iter().map(|model| model.mesh.into()).collect_vec()),
+                materials,
+                source: Some(ModelSource::Obj(obj_path.to_path_buf())),
+                ..self
+            },
+            Err(_) => self,
         }
     }
 
@@ -105,6 +162,7 @@ impl<VertexDefinition, VertexType, IndexType, Layout, RenderP> ModelBuilder<Vert
         where
             VertexType: Send + Sync + 'static,
             IndexType: Send + Sync + 'static,
+            GraphicsPipeline<VertexDefinition, Layout, RenderP>: GraphicsPipelineAbstract + Send + Sync + 'static,
     {
         if self.meshes.is_none() {
             return Err(MissingMeshes);
@@ -112,6 +170,8 @@ impl<VertexDefinition, VertexType, IndexType, Layout, RenderP> ModelBuilder<Vert
 
         let meshes = self.meshes.unwrap();
 
+        let material_set = self.load_material_set(&meshes);
+
         let vertices = meshes.clone().iter().flat_map(|mesh| mesh.clone().vertices.clone()).collect_vec();
         let indices = meshes.clone().iter().flat_map(|mesh| mesh.clone().indices.clone()).collect_vec();
 
@@ -122,22 +182,411 @@ impl<VertexDefinition, VertexType, IndexType, Layout, RenderP> ModelBuilder<Vert
             indices.into_iter().clone(), BufferUsage::index_buffer(), self.queue.clone(),
         ).unwrap();
         Ok(Model {
-            vertex_buffer: vec![vertex_buffer.0.clone()],
-            index_buffer: index_buffer.0.clone(),
+            vertex_buffer: Arc::new(Mutex::new(vec![vertex_buffer.0.clone()])),
+            index_buffer: Arc::new(Mutex::new(index_buffer.0.clone())),
             pipeline: self.pipeline.clone(),
+            instance_buffer: self.instance_buffer.clone(),
+            material_set,
+            source: self.source.clone(),
             phantom: PhantomData::default(),
         })
     }
+
+    /// Upload the diffuse texture of the first referenced material that has one as an
+    /// [`ImmutableImage`] and bundle it, together with a repeating linear sampler, into a
+    /// descriptor set bound at set 0.
+    ///
+    /// Returns `None` when no mesh references a material with a diffuse texture, or the upload fails.
+    fn load_material_set(&self, meshes: &[Mesh<VertexType, IndexType>]) -> Option<Arc<DescriptorSet + Send + Sync + 'static>>
+        where
+            GraphicsPipeline<VertexDefinition, Layout, RenderP>: GraphicsPipelineAbstract + Send + Sync + 'static,
+    {
+        let material_ids = meshes.iter().map(|mesh| mesh.material_id).collect_vec();
+        build_material_set(&self.queue, &self.pipeline, &self.source, &self.materials, &material_ids)
+    }
+
+    /// Upload the model's geometry on a background [`UploadWorker`] instead of blocking the render
+    /// thread on `ImmutableBuffer::from_iter`.
+    ///
+    /// Returns a [`ModelUpload`] immediately; the transfer runs on the worker's transfer queue and
+    /// the finished [`Model`] only becomes available once its upload fence has signalled. Poll the
+    /// handle from the render loop and skip drawing until it yields a model.
+    pub fn build_async(self, worker: &UploadWorker) -> Result<ModelUpload<VertexDefinition, VertexType, IndexType, Layout, RenderP>, ModelBuilderError>
+        where
+            VertexDefinition: Send + Sync + 'static,
+            Layout: Send + Sync + 'static,
+            RenderP: Send + Sync + 'static,
+            VertexType: Send + Sync + 'static,
+            IndexType: Send + Sync + 'static,
+            GraphicsPipeline<VertexDefinition, Layout, RenderP>: GraphicsPipelineAbstract + Send + Sync + 'static,
+    {
+        if self.meshes.is_none() {
+            return Err(MissingMeshes);
+        }
+
+        let meshes = self.meshes.unwrap();
+
+        let vertices = meshes.clone().iter().flat_map(|mesh| mesh.clone().vertices.clone()).collect_vec();
+        let indices = meshes.clone().iter().flat_map(|mesh| mesh.clone().indices.clone()).collect_vec();
+        let material_ids = meshes.iter().map(|mesh| mesh.material_id).collect_vec();
+
+        let pipeline = self.pipeline.clone();
+        let instance_buffer = self.instance_buffer.clone();
+        let source = self.source.clone();
+        let materials = self.materials.clone();
+
+        let (sender, receiver) = channel();
+
+        let submitted = worker.submit(Box::new(move |queue| {
+            let vertex_buffer = ImmutableBuffer::from_iter(
+                vertices.into_iter().clone(), BufferUsage::vertex_buffer(), queue.clone()
+            ).unwrap();
+            let index_buffer = ImmutableBuffer::from_iter(
+                indices.into_iter().clone(), BufferUsage::index_buffer(), queue.clone(),
+            ).unwrap();
+
+            // Block the worker thread (not the render thread) until both transfers have completed.
+            vertex_buffer.1.join(index_buffer.1).then_signal_fence_and_flush().unwrap().wait(None).unwrap();
+
+            // Upload the material/texture on the worker too, so the async path is not geometry-only.
+            let material_set = build_material_set(queue, &pipeline, &source, &materials, &material_ids);
+
+            let _ = sender.send(Model {
+                vertex_buffer: Arc::new(Mutex::new(vec![vertex_buffer.0.clone()])),
+                index_buffer: Arc::new(Mutex::new(index_buffer.0.clone())),
+                pipeline,
+                instance_buffer,
+                material_set,
+                source,
+                phantom: PhantomData::default(),
+            });
+        }));
+
+        if !submitted {
+            return Err(ModelBuilderError::WorkerUnavailable);
+        }
+
+        Ok(ModelUpload { receiver, ready: None })
+    }
+}
+
+/// Handle to a [`Model`] whose geometry is being uploaded on the [`UploadWorker`].
+///
+/// The model is not drawable until its transfer fence has signalled. Call [`ModelUpload::poll`]
+/// (or [`ModelUpload::is_ready`]) each frame from the render loop and skip the model until it is
+/// ready, then take ownership with [`ModelUpload::into_model`].
+pub struct ModelUpload<VertexDefinition, VertexType, IndexType, Layout, RenderP> {
+    receiver: Receiver<Model<VertexDefinition, VertexType, IndexType, Layout, RenderP>>,
+    ready:    Option<Model<VertexDefinition, VertexType, IndexType, Layout, RenderP>>,
+}
+
+impl<VertexDefinition, VertexType, IndexType, Layout, RenderP> ModelUpload<VertexDefinition, VertexType, IndexType, Layout, RenderP> {
+    fn try_recv(&mut self) {
+        if self.ready.is_none() {
+            if let Ok(model) = self.receiver.try_recv() {
+                self.ready = Some(model);
+            }
+        }
+    }
+
+    /// Returns `true` once the upload has completed and the model is drawable.
+    pub fn is_ready(&mut self) -> bool {
+        self.try_recv();
+        self.ready.is_some()
+    }
+
+    /// Borrow the finished model, or `None` while the upload is still in flight.
+    pub fn poll(&mut self) -> Option<&Model<VertexDefinition, VertexType, IndexType, Layout, RenderP>> {
+        self.try_recv();
+        self.ready.as_ref()
+    }
+
+    /// Take ownership of the finished model, or `None` while the upload is still in flight.
+    pub fn into_model(mut self) -> Option<Model<VertexDefinition, VertexType, IndexType, Layout, RenderP>> {
+        self.try_recv();
+        self.ready.take()
+    }
+}
+
+impl<VertexDefinition, Layout, RenderP> ModelBuilder<VertexDefinition, Vertex, u32, Layout, RenderP> {
+    /// Load geometry from a glTF 2.0 file, feeding the same `meshes` pipeline as [`with_obj_path`].
+    ///
+    /// [`with_obj_path`]: ModelBuilder::with_obj_path
+    pub fn with_gltf_path(self, gltf_path: &Path) -> Self {
+        match gltf::import(gltf_path) {
+            Ok((document, buffers, _images)) => Self {
+                source: Some(ModelSource::Gltf(gltf_path.to_path_buf())),
+                ..self.with_gltf(&document, &buffers)
+            },
+            Err(_) => self,
+        }
+    }
+
+    /// Flatten a parsed glTF scene graph into meshes, baking each node's world transform into the
+    /// emitted vertices so a glTF file produces the same [`Model`] output as an OBJ file.
+    ///
+    /// Primitives without an index buffer get a sequential index list, and primitives missing
+    /// normals have them computed from face geometry rather than asserting on equal lengths.
+    pub fn with_gltf(self, document: &gltf::Document, buffers: &[gltf::buffer::Data]) -> Self {
+        let identity = [
+            [1.0, 0.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ];
+
+        let mut meshes = Vec::new();
+        for scene in document.scenes() {
+            for node in scene.nodes() {
+                Self::flatten_gltf_node(&node, identity, buffers, &mut meshes);
+            }
+        }
+
+        Self {
+            meshes: Some(meshes),
+            ..self
+        }
+    }
+
+    /// Flatten a glTF node (and its children) into meshes with their transforms baked in.
+    ///
+    /// `material_id` is left `None`: the builder's `materials` table is populated only from the OBJ
+    /// loader (`tobj`), so texturing is an OBJ-only feature. A glTF primitive's material index refers
+    /// to the glTF document's own material array, which would alias the wrong table if stored here.
+    fn flatten_gltf_node(node: &gltf::Node, parent: [[f32; 4]; 4], buffers: &[gltf::buffer::Data], out: &mut Vec<Mesh<Vertex, u32>>) {
+        let world = mat4_mul(parent, node.transform().matrix());
+
+        if let Some(mesh) = node.mesh() {
+            for primitive in mesh.primitives() {
+                let reader = primitive.reader(|buffer| buffers.get(buffer.index()).map(|data| data.0.as_slice()));
+
+                let positions: Vec<[f32; 3]> = match reader.read_positions() {
+                    Some(iter) => iter.collect(),
+                    None => continue,
+                };
+
+                let indices: Vec<u32> = match reader.read_indices() {
+                    Some(ReadIndices::U8(iter)) => iter.map(|i| i as u32).collect(),
+                    Some(ReadIndices::U16(iter)) => iter.map(|i| i as u32).collect(),
+                    Some(ReadIndices::U32(iter)) => iter.collect(),
+                    None => (0..positions.len() as u32).collect(),
+                };
+
+                let normals = reader
+                    .read_normals()
+                    .map(|iter| iter.collect::<Vec<[f32; 3]>>())
+                    .unwrap_or_else(|| compute_normals(&positions, &indices));
+
+                let uvs = reader
+                    .read_tex_coords(0)
+                    .map(|iter| iter.into_f32().collect::<Vec<[f32; 2]>>())
+                    .unwrap_or_else(|| vec![[0.0, 0.0]; positions.len()]);
+
+                let vertices = positions
+                    .iter()
+                    .enumerate()
+                    .map(|(i, position)| Vertex {
+                        position: transform_point(world, *position),
+                        normals: transform_normal(world, normals.get(i).copied().unwrap_or([0.0, 0.0, 0.0])),
+                        uv: uvs.get(i).copied().unwrap_or([0.0, 0.0]),
+                    })
+                    .collect_vec();
+
+                out.push(Mesh {
+                    vertices,
+                    indices,
+                    material_id: None,
+                });
+            }
+        }
+
+        for child in node.children() {
+            Self::flatten_gltf_node(&child, world, buffers, out);
+        }
+    }
+}
+
+/// Upload the diffuse texture of the first referenced material that has one and bundle it, with a
+/// repeating linear sampler, into a descriptor set bound at set 0.
+///
+/// The `diffuse_texture` path is taken as written in the `.mtl` (i.e. relative to the source
+/// file), so it is resolved against the source's parent directory before opening — opening it
+/// relative to the process CWD would fail for any real asset. Returns `None` when nothing
+/// references a material with a diffuse texture, or the upload fails.
+fn build_material_set<VertexDefinition, Layout, RenderP>(
+    queue: &Arc<Queue>,
+    pipeline: &Arc<GraphicsPipeline<VertexDefinition, Layout, RenderP>>,
+    source: &Option<ModelSource>,
+    materials: &[tobj::Material],
+    material_ids: &[Option<usize>],
+) -> Option<Arc<DescriptorSet + Send + Sync + 'static>>
+    where
+        GraphicsPipeline<VertexDefinition, Layout, RenderP>: GraphicsPipelineAbstract + Send + Sync + 'static,
+{
+    let material = material_ids
+        .iter()
+        .filter_map(|id| *id)
+        .filter_map(|id| materials.get(id))
+        .find(|material| !material.diffuse_texture.is_empty())?;
+
+    let texture_path = match source {
+        Some(source) => source
+            .path()
+            .parent()
+            .map(|dir| dir.join(&material.diffuse_texture))
+            .unwrap_or_else(|| PathBuf::from(&material.diffuse_texture)),
+        None => PathBuf::from(&material.diffuse_texture),
+    };
+
+    let image = image::open(&texture_path).ok()?.to_rgba();
+    let (width, height) = image.dimensions();
+
+    let (texture, _future) = ImmutableImage::from_iter(
+        image.into_raw().into_iter(),
+        Dimensions::Dim2d { width, height },
+        Format::R8G8B8A8Srgb,
+        queue.clone(),
+    ).ok()?;
+
+    let sampler = Sampler::simple_repeat_linear(queue.device().clone());
+
+    let set = PersistentDescriptorSet::start(pipeline.clone(), 0)
+        .add_sampled_image(texture, sampler).ok()?
+        .build().ok()?;
+
+    Some(Arc::new(set))
+}
+
+/// Multiply two column-major 4x4 matrices (`a * b`).
+fn mat4_mul(a: [[f32; 4]; 4], b: [[f32; 4]; 4]) -> [[f32; 4]; 4] {
+    let mut result = [[0.0f32; 4]; 4];
+    for col in 0..4 {
+        for row in 0..4 {
+            result[col][row] = (0..4).map(|k| a[k][row] * b[col][k]).sum();
+        }
+    }
+    result
+}
+
+/// Transform a position by a column-major 4x4 matrix (implicit `w = 1`).
+fn transform_point(m: [[f32; 4]; 4], p: [f32; 3]) -> [f32; 3] {
+    [
+        m[0][0] * p[0] + m[1][0] * p[1] + m[2][0] * p[2] + m[3][0],
+        m[0][1] * p[0] + m[1][1] * p[1] + m[2][1] * p[2] + m[3][1],
+        m[0][2] * p[0] + m[1][2] * p[1] + m[2][2] * p[2] + m[3][2],
+    ]
+}
+
+/// Transform a normal by the upper-left 3x3 of a column-major 4x4 matrix and renormalize.
+fn transform_normal(m: [[f32; 4]; 4], n: [f32; 3]) -> [f32; 3] {
+    let transformed = [
+        m[0][0] * n[0] + m[1][0] * n[1] + m[2][0] * n[2],
+        m[0][1] * n[0] + m[1][1] * n[1] + m[2][1] * n[2],
+        m[0][2] * n[0] + m[1][2] * n[1] + m[2][2] * n[2],
+    ];
+    normalize(transformed)
+}
+
+fn normalize(v: [f32; 3]) -> [f32; 3] {
+    let len = (v[0] * v[0] + v[1] * v[1] + v[2] * v[2]).sqrt();
+    if len > 0.0 {
+        [v[0] / len, v[1] / len, v[2] / len]
+    } else {
+        v
+    }
+}
+
+/// Compute per-vertex normals from face geometry for primitives that ship without them.
+fn compute_normals(positions: &[[f32; 3]], indices: &[u32]) -> Vec<[f32; 3]> {
+    let mut normals = vec![[0.0f32; 3]; positions.len()];
+
+    for triangle in indices.chunks_exact(3) {
+        let (a, b, c) = (triangle[0] as usize, triangle[1] as usize, triangle[2] as usize);
+        let (pa, pb, pc) = (positions[a], positions[b], positions[c]);
+
+        let u = [pb[0] - pa[0], pb[1] - pa[1], pb[2] - pa[2]];
+        let v = [pc[0] - pa[0], pc[1] - pa[1], pc[2] - pa[2]];
+        let face = [
+            u[1] * v[2] - u[2] * v[1],
+            u[2] * v[0] - u[0] * v[2],
+            u[0] * v[1] - u[1] * v[0],
+        ];
+
+        for &index in &[a, b, c] {
+            normals[index][0] += face[0];
+            normals[index][1] += face[1];
+            normals[index][2] += face[2];
+        }
+    }
+
+    normals.into_iter().map(normalize).collect()
+}
+
+/// A [`DescriptorSetsCollection`] that prepends a single material set at set 0 and shifts the
+/// caller's `sets` to the following set indices.
+///
+/// This lets [`Model::draw`] keep accepting an arbitrary collection of caller sets (a tuple, `()`,
+/// a single set, …) while still binding its own material set at set 0 for textured models, without
+/// forcing every caller to thread the material through by hand.
+struct WithMaterial<S> {
+    material: (Arc<DescriptorSet + Send + Sync + 'static>,),
+    sets: S,
+}
+
+unsafe impl<S> DescriptorSetsCollection for WithMaterial<S>
+    where S: DescriptorSetsCollection
+{
+    fn into_vec(self) -> Vec<Box<DescriptorSet + Send + Sync>> {
+        let mut sets = self.material.into_vec();
+        sets.extend(self.sets.into_vec());
+        sets
+    }
+
+    fn num_bindings_in_set(&self, set: usize) -> Option<usize> {
+        match set {
+            0 => self.material.num_bindings_in_set(0),
+            set => self.sets.num_bindings_in_set(set - 1),
+        }
+    }
+
+    fn descriptor(&self, set: usize, binding: usize) -> Option<DescriptorDesc> {
+        match set {
+            0 => self.material.descriptor(0, binding),
+            set => self.sets.descriptor(set - 1, binding),
+        }
+    }
 }
 
 pub trait Drawable {
-    fn draw<S: DescriptorSetsCollection>(&self, cmd_buf: AutoCommandBufferBuilder, dynamic_state: &DynamicState, sets: S) -> Result<AutoCommandBufferBuilder, DrawIndexedError>;
+    /// Record a draw and return, from the *same* buffer snapshot, the type-erased `Arc` resources it
+    /// referenced for keep-alive tracking.
+    ///
+    /// Taking both in one call is what makes keep-alive sound under hot-reloading: a reload may swap
+    /// the `vertex_buffer`/`index_buffer` `Arc`s at any time, so recording the draw and collecting
+    /// the handles from two independent snapshots could capture one buffer while drawing another.
+    ///
+    /// See [`draw`](Drawable::draw) for the `sets` set-index contract.
+    fn draw_tracked<S: DescriptorSetsCollection>(&self, cmd_buf: AutoCommandBufferBuilder, dynamic_state: &DynamicState, sets: S) -> Result<(AutoCommandBufferBuilder, Vec<Arc<Any + Send + Sync + 'static>>), DrawIndexedError>;
+
+    /// Record a draw of this drawable.
+    ///
+    /// `sets` is the caller's descriptor-set collection (a tuple, `()`, a single set, …) holding the
+    /// per-draw data such as a view/transform UBO. Set-index contract: a textured model binds its
+    /// own material set at set 0 and shifts the caller's `sets` to set 1 and up; an untextured model
+    /// binds `sets` starting at set 0. The material is prepended internally via [`WithMaterial`], so
+    /// callers never have to thread it through themselves.
+    fn draw<S: DescriptorSetsCollection>(&self, cmd_buf: AutoCommandBufferBuilder, dynamic_state: &DynamicState, sets: S) -> Result<AutoCommandBufferBuilder, DrawIndexedError> {
+        self.draw_tracked(cmd_buf, dynamic_state, sets).map(|(builder, _)| builder)
+    }
 }
 
 pub struct Model<VertexDefinition, VertexType, IndexType, Layout, RenderP> {
-    pub vertex_buffer: Vec<Arc<BufferAccess + Send + Sync + 'static>>,
-    pub index_buffer: Arc<TypedBufferAccess<Content = [IndexType]> + Sync + Send + 'static>,
+    pub vertex_buffer: Arc<Mutex<Vec<Arc<BufferAccess + Send + Sync + 'static>>>>,
+    pub index_buffer: Arc<Mutex<Arc<TypedBufferAccess<Content = [IndexType]> + Sync + Send + 'static>>>,
     pub pipeline: Arc<GraphicsPipeline<VertexDefinition, Layout, RenderP>>,
+    pub instance_buffer: Option<Arc<CpuAccessibleBuffer<[InstanceData]>>>,
+    pub material_set: Option<Arc<DescriptorSet + Send + Sync + 'static>>,
+    pub source: Option<ModelSource>,
     phantom: PhantomData<VertexType>,
 }
 
@@ -150,9 +599,64 @@ impl<VertexDef, VertexType, IndexType, Layout, RenderP> Drawable for Model<Verte
         Arc<TypedBufferAccess<Content = [IndexType]>>: BufferAccess,
         GraphicsPipeline<VertexDef, Layout, RenderP>: GraphicsPipelineAbstract + VertexSource<(Vec<Arc<BufferAccess + Send + Sync>>)>,
 {
-    fn draw<S: DescriptorSetsCollection>(&self, cmd_buf: AutoCommandBufferBuilder, dynamic_state: &DynamicState, sets: S) -> Result<AutoCommandBufferBuilder, DrawIndexedError>
+    fn draw_tracked<S: DescriptorSetsCollection>(&self, cmd_buf: AutoCommandBufferBuilder, dynamic_state: &DynamicState, sets: S) -> Result<(AutoCommandBufferBuilder, Vec<Arc<Any + Send + Sync + 'static>>), DrawIndexedError>
     {
-        cmd_buf.draw_indexed(self.pipeline.clone(), dynamic_state, self.vertex_buffer.clone(), self.index_buffer.clone(), sets, ())
+        // Snapshot the (possibly hot-reloaded) buffers once, then build both the draw and the
+        // keep-alive handle list from these exact clones — a concurrent reload can no longer leave
+        // the handles referencing a different buffer than the one the draw recorded.
+        let vertex_snapshot = self.vertex_buffer.lock().unwrap().clone();
+        let index_buffer = self.index_buffer.lock().unwrap().clone();
+
+        let mut vertex_buffer = vertex_snapshot.clone();
+        // Append the per-instance transform buffer as a second vertex buffer when present, so a model
+        // built with `with_instance_buffer` is drawn instanced through the same path as a plain draw.
+        if let Some(instance_buffer) = self.instance_buffer.clone() {
+            vertex_buffer.push(instance_buffer);
+        }
+
+        let mut handles: Vec<Arc<Any + Send + Sync + 'static>> = vec![
+            Arc::new(vertex_snapshot),
+            Arc::new(index_buffer.clone()),
+            Arc::new(self.pipeline.clone()),
+        ];
+        if let Some(instance_buffer) = self.instance_buffer.clone() {
+            handles.push(Arc::new(instance_buffer));
+        }
+        if let Some(material_set) = self.material_set.clone() {
+            handles.push(Arc::new(material_set));
+        }
+
+        // A textured model prepends its material at set 0 and shifts the caller's `sets` up by one;
+        // an untextured model binds `sets` unchanged. See the `Drawable::draw` set-index contract.
+        let builder = match self.material_set.clone() {
+            Some(material_set) => cmd_buf.draw_indexed(self.pipeline.clone(), dynamic_state, vertex_buffer, index_buffer, WithMaterial { material: (material_set,), sets }, ())?,
+            None => cmd_buf.draw_indexed(self.pipeline.clone(), dynamic_state, vertex_buffer, index_buffer, sets, ())?,
+        };
+
+        Ok((builder, handles))
+    }
+}
+
+impl<VertexDef, VertexType, IndexType, Layout, RenderP> Model<VertexDef, VertexType, IndexType, Layout, RenderP>
+    where
+        Layout: Sync + Send + 'static,
+        RenderP: Sync + Send + 'static,
+        VertexDef: Sync + Send + 'static,
+        IndexType: Index + Sized + Sync + Send + 'static,
+        Arc<TypedBufferAccess<Content = [IndexType]>>: BufferAccess,
+        GraphicsPipeline<VertexDef, Layout, RenderP>: GraphicsPipelineAbstract + VertexSource<(Vec<Arc<BufferAccess + Send + Sync>>)>,
+{
+    /// Draw every instance described by the attached instance buffer in a single indexed draw.
+    ///
+    /// The per-instance transform buffer is bound as a second vertex buffer after the mesh
+    /// geometry; the instance count is taken from its length, so positioning many entities costs
+    /// one `draw_indexed` call rather than one per object. This is a thin alias for [`draw`](Drawable::draw),
+    /// which already appends the instance buffer when one was attached through
+    /// [`ModelBuilder::with_instance_buffer`] — routing both through the same binding logic so
+    /// instanced draws get the material set and, via [`RenderDrawable`], keep-alive tracking.
+    pub fn draw_instanced<S: DescriptorSetsCollection>(&self, cmd_buf: AutoCommandBufferBuilder, dynamic_state: &DynamicState, sets: S) -> Result<AutoCommandBufferBuilder, DrawIndexedError>
+    {
+        self.draw(cmd_buf, dynamic_state, sets)
     }
 }
 
@@ -177,17 +681,167 @@ impl RenderDrawable for AutoCommandBufferBuilder {
 
 }
 
+/// An [`AutoCommandBufferBuilder`] that keeps every `Arc` resource its draws touch alive for the
+/// lifetime of the recorded command buffer.
+///
+/// Each `draw_drawable` accumulates the handles returned by [`Drawable::draw_tracked`] into `stored_handles` and
+/// bumps a call counter; [`build`](TrackedCommandBufferBuilder::build) moves the handles into the
+/// finished [`TrackedCommandBuffer`], so they are only dropped once GPU execution has completed —
+/// preventing a use-after-free when a [`Model`] is dropped while its draws are still in flight.
+pub struct TrackedCommandBufferBuilder {
+    inner:          AutoCommandBufferBuilder,
+    stored_handles: Vec<Arc<Any + Send + Sync + 'static>>,
+    call_count:     AtomicUsize,
+}
+
+impl TrackedCommandBufferBuilder {
+    pub fn new(inner: AutoCommandBufferBuilder) -> Self {
+        TrackedCommandBufferBuilder {
+            inner,
+            stored_handles: Vec::new(),
+            call_count:     AtomicUsize::new(0),
+        }
+    }
+
+    /// Number of draws recorded into this builder so far.
+    pub fn call_count(&self) -> usize {
+        self.call_count.load(Ordering::SeqCst)
+    }
+
+    /// Finish recording, moving the accumulated handles into the command buffer so they can be
+    /// handed to — and owned by — the GPU-execution future.
+    pub fn build(self) -> Result<TrackedCommandBuffer, BuildError> {
+        Ok(TrackedCommandBuffer {
+            inner:          self.inner.build()?,
+            stored_handles: self.stored_handles,
+        })
+    }
+}
+
+impl RenderDrawable for TrackedCommandBufferBuilder {
+    type Error = DrawIndexedError;
+
+    fn draw_drawable<T: Drawable, S: DescriptorSetsCollection>(mut self, drawable: &T, dynamic_state: &DynamicState, sets: S) -> Result<Self, Self::Error>
+        where
+            Self: Sized
+    {
+        self.call_count.fetch_add(1, Ordering::SeqCst);
+
+        // Record the draw and capture its handles from one buffer snapshot, so hot-reloading cannot
+        // desync the kept-alive resources from what was actually drawn.
+        let (inner, handles) = drawable.draw_tracked(self.inner, dynamic_state, sets)?;
+        self.stored_handles.extend(handles);
+
+        Ok(TrackedCommandBufferBuilder {
+            inner,
+            stored_handles: self.stored_handles,
+            call_count:     self.call_count,
+        })
+    }
+}
+
+/// A finished command buffer together with the `Arc` handles its draws referenced.
+///
+/// Executing it with [`execute`](TrackedCommandBuffer::execute) moves both the command buffer and
+/// its handles into the returned [`TrackedExecFuture`], so the handles are owned by the execution
+/// future and only dropped once the GPU work has completed — not left behind on submit.
+pub struct TrackedCommandBuffer {
+    inner:          AutoCommandBuffer,
+    stored_handles: Vec<Arc<Any + Send + Sync + 'static>>,
+}
+
+impl TrackedCommandBuffer {
+    /// Submit the command buffer for execution on `queue`, handing the kept-alive handles to the
+    /// returned future so they outlive the GPU work that references them.
+    pub fn execute(self, queue: Arc<Queue>) -> Result<TrackedExecFuture<impl GpuFuture>, CommandBufferExecError> {
+        let future = self.inner.execute(queue)?;
+        Ok(TrackedExecFuture {
+            future,
+            stored_handles: self.stored_handles,
+        })
+    }
+}
+
+/// A GPU-execution future that owns the `Arc` handles referenced by the command buffer it ran.
+///
+/// It delegates every [`GpuFuture`] operation to the wrapped future and carries the handles
+/// alongside. The struct's fields drop in declaration order, so the wrapped `future` is cleaned up
+/// first (its `Drop` synchronises with the GPU) and only then do `stored_handles` drop — keeping
+/// the resources alive until the GPU work referencing them is done.
+pub struct TrackedExecFuture<F> {
+    future:         F,
+    stored_handles: Vec<Arc<Any + Send + Sync + 'static>>,
+}
+
+unsafe impl<F> DeviceOwned for TrackedExecFuture<F>
+    where F: GpuFuture
+{
+    fn device(&self) -> &Arc<Device> {
+        self.future.device()
+    }
+}
+
+unsafe impl<F> GpuFuture for TrackedExecFuture<F>
+    where F: GpuFuture
+{
+    fn cleanup_finished(&mut self) {
+        self.future.cleanup_finished()
+    }
+
+    unsafe fn build_submission(&self) -> Result<SubmitAnyBuilder, FlushError> {
+        self.future.build_submission()
+    }
+
+    fn flush(&self) -> Result<(), FlushError> {
+        self.future.flush()
+    }
+
+    unsafe fn signal_finished(&self) {
+        self.future.signal_finished()
+    }
+
+    fn queue_change_allowed(&self) -> bool {
+        self.future.queue_change_allowed()
+    }
+
+    fn queue(&self) -> Option<Arc<Queue>> {
+        self.future.queue()
+    }
+
+    fn check_buffer_access(&self, buffer: &BufferAccess, exclusive: bool, queue: &Queue) -> Result<Option<(PipelineStages, AccessFlagBits)>, AccessCheckError> {
+        self.future.check_buffer_access(buffer, exclusive, queue)
+    }
+
+    fn check_image_access(&self, image: &ImageAccess, layout: ImageLayout, exclusive: bool, queue: &Queue) -> Result<Option<(PipelineStages, AccessFlagBits)>, AccessCheckError> {
+        self.future.check_image_access(image, layout, exclusive, queue)
+    }
+}
+
 
 #[derive(Clone)]
 pub struct Mesh<VertexDefinition, IndexDefinition> {
     pub vertices: Vec<VertexDefinition>,
     pub indices:  Vec<IndexDefinition>,
+    pub material_id: Option<usize>,
 }
 
 #[derive(Copy, Clone)]
 pub struct Vertex {
     pub position: [f32; 3],
     pub normals: [f32; 3],
+    pub uv: [f32; 2],
+}
+
+vulkano::impl_vertex!(Vertex, position, normals, uv);
+
+/// Per-instance vertex data bound as a second vertex buffer for instanced draws.
+///
+/// The `mat4` is exposed to the shader across four consecutive `vec4` attribute locations by
+/// `impl_vertex!`, so the vertex stage can read a per-instance `model` matrix and compute
+/// `view * model * vec4(position, 1.0)`.
+#[derive(Copy, Clone)]
+pub struct InstanceData {
+    pub model: [[f32; 4]; 4],
 }
 
-vulkano::impl_vertex!(Vertex, position, normals);
+vulkano::impl_vertex!(InstanceData, model);
@@ -0,0 +1,50 @@
+use std::sync::Arc;
+use std::sync::mpsc::{channel, Sender, Receiver};
+use std::thread::{self, JoinHandle};
+use vulkano::device::Queue;
+
+/// A unit of work handed to the [`UploadWorker`]. It is run on the worker thread and receives the
+/// worker's transfer [`Queue`] so the actual `ImmutableBuffer::from_iter` transfer happens off the
+/// render thread.
+pub type UploadJob = Box<dyn FnOnce(&Arc<Queue>) + Send + 'static>;
+
+/// Dedicated upload thread owning a transfer [`Queue`].
+///
+/// Submitting a geometry upload as an [`UploadJob`] keeps the blocking transfer off the graphics
+/// thread, so a caller can kick off many model loads concurrently instead of serialising them on
+/// the graphics queue.
+pub struct UploadWorker {
+    sender: Option<Sender<UploadJob>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl UploadWorker {
+    pub fn new(queue: Arc<Queue>) -> Self {
+        let (sender, receiver): (Sender<UploadJob>, Receiver<UploadJob>) = channel();
+        let handle = thread::spawn(move || {
+            for job in receiver.iter() {
+                job(&queue);
+            }
+        });
+
+        UploadWorker {
+            sender: Some(sender),
+            handle: Some(handle),
+        }
+    }
+
+    /// Enqueue a job to run on the worker thread. Returns `false` if the worker has shut down.
+    pub fn submit(&self, job: UploadJob) -> bool {
+        self.sender.as_ref().map(|sender| sender.send(job).is_ok()).unwrap_or(false)
+    }
+}
+
+impl Drop for UploadWorker {
+    fn drop(&mut self) {
+        // Drop the sender first so the receiver loop ends, then join to let in-flight uploads finish.
+        self.sender.take();
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}